@@ -0,0 +1,292 @@
+// Copyright 2015-2019 Capital One Services, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use codec::core::CapabilityConfiguration;
+use r2d2::{ManageConnection, Pool};
+use redis::ConnectionLike;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A minimal `r2d2::ManageConnection` over `redis::Client`, so single-node
+/// connections can be pooled without pulling in `r2d2_redis` -- that crate
+/// vendors its own old, pinned `redis` major, which would resolve as a
+/// distinct crate instance from the `redis::cluster`/`redis::sentinel` this
+/// module also depends on, and their `ConnectionLike` trait objects are not
+/// interchangeable.
+pub struct RedisConnectionManager {
+    client: redis::Client,
+}
+
+impl RedisConnectionManager {
+    pub fn new(client: redis::Client) -> Self {
+        RedisConnectionManager { client }
+    }
+}
+
+impl ManageConnection for RedisConnectionManager {
+    type Connection = redis::Connection;
+    type Error = redis::RedisError;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        self.client.get_connection()
+    }
+
+    fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        redis::cmd("PING").query(conn)
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        !conn.is_open()
+    }
+}
+
+/// A thin wrapper around `r2d2::PooledConnection<RedisConnectionManager>`
+/// that implements `ConnectionLike`. `PooledConnection` derefs to
+/// `redis::Connection`, but that alone doesn't make it a `ConnectionLike` --
+/// and since both the trait and `PooledConnection` are foreign, we can't
+/// `impl ConnectionLike for PooledConnection<..>` directly (orphan rules).
+/// This local newtype satisfies them by delegating each method through the
+/// deref coercion.
+pub struct PooledRedisConnection(r2d2::PooledConnection<RedisConnectionManager>);
+
+impl ConnectionLike for PooledRedisConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<redis::Value> {
+        self.0.req_packed_command(cmd)
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        self.0.req_packed_commands(cmd, offset, count)
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.get_db()
+    }
+
+    fn check_connection(&mut self) -> bool {
+        self.0.check_connection()
+    }
+
+    fn is_open(&self) -> bool {
+        self.0.is_open()
+    }
+}
+
+/// A connection for whichever Redis topology an actor's module was set up
+/// for, implementing `ConnectionLike` directly (rather than type-erasing
+/// into `Box<dyn ConnectionLike>`, which the `redis` crate provides no
+/// blanket impl for) by delegating to the active variant.
+pub enum ActorConnection {
+    Single(PooledRedisConnection),
+    Cluster(redis::cluster::ClusterConnection),
+    Sentinel(redis::Connection),
+}
+
+impl ConnectionLike for ActorConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<redis::Value> {
+        match self {
+            ActorConnection::Single(c) => c.req_packed_command(cmd),
+            ActorConnection::Cluster(c) => c.req_packed_command(cmd),
+            ActorConnection::Sentinel(c) => c.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        match self {
+            ActorConnection::Single(c) => c.req_packed_commands(cmd, offset, count),
+            ActorConnection::Cluster(c) => c.req_packed_commands(cmd, offset, count),
+            ActorConnection::Sentinel(c) => c.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            ActorConnection::Single(c) => c.get_db(),
+            ActorConnection::Cluster(c) => c.get_db(),
+            ActorConnection::Sentinel(c) => c.get_db(),
+        }
+    }
+
+    fn check_connection(&mut self) -> bool {
+        match self {
+            ActorConnection::Single(c) => c.check_connection(),
+            ActorConnection::Cluster(c) => c.check_connection(),
+            ActorConnection::Sentinel(c) => c.check_connection(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        match self {
+            ActorConnection::Single(c) => c.is_open(),
+            ActorConnection::Cluster(c) => c.is_open(),
+            ActorConnection::Sentinel(c) => c.is_open(),
+        }
+    }
+}
+
+const ENV_REDIS_URL: &str = "URL";
+const ENV_REDIS_MODE: &str = "MODE";
+const ENV_CLUSTER_URLS: &str = "CLUSTER_URLS";
+const ENV_SENTINEL_ADDRESSES: &str = "SENTINEL_ADDRESSES";
+const ENV_SENTINEL_MASTER: &str = "SENTINEL_MASTER";
+const ENV_POOL_SIZE: &str = "POOL_SIZE";
+const ENV_POOL_TIMEOUT_MS: &str = "POOL_TIMEOUT_MS";
+
+const MODE_CLUSTER: &str = "cluster";
+const MODE_SENTINEL: &str = "sentinel";
+
+const DEFAULT_REDIS_URL: &str = "redis://0.0.0.0:6379/";
+const DEFAULT_POOL_SIZE: u32 = 10;
+const DEFAULT_POOL_TIMEOUT_MS: u64 = 5_000;
+
+/// A configured connection to whichever Redis topology an actor's module was
+/// set up for. Holding this (rather than a bare `redis::Client`) lets
+/// `actor_con` hand callers a uniform `ActorConnection` no matter which
+/// variant backs it.
+pub enum RedisConnection {
+    /// A single Redis node, the default when no `MODE` is configured. Regular
+    /// operations check out a connection from `pool` instead of dialing a
+    /// fresh one per call; `client` is kept alongside it so `OP_WATCH` can
+    /// still open its own dedicated, long-lived pub/sub connection outside
+    /// the pool.
+    Single {
+        client: redis::Client,
+        pool: Pool<RedisConnectionManager>,
+    },
+    /// A Redis Cluster, reached through a set of seed node URLs.
+    Cluster(redis::cluster::ClusterClient),
+    /// A Sentinel-monitored master, resolved through a set of sentinel
+    /// addresses and a master group name. Wrapped in a `Mutex` because
+    /// `SentinelClient::get_connection` needs `&mut self` to re-resolve the
+    /// master after a failover.
+    Sentinel(Mutex<redis::sentinel::SentinelClient>),
+}
+
+impl RedisConnection {
+    /// Returns a connection for this topology -- a pooled one for `Single`
+    /// (wrapped in `PooledRedisConnection` so it satisfies `ConnectionLike`),
+    /// freshly dialed otherwise. All existing `Commands`-based operations
+    /// keep working unchanged, since `ActorConnection` implements
+    /// `ConnectionLike` itself.
+    pub fn get_connection(&self) -> Result<ActorConnection, Box<dyn Error>> {
+        match self {
+            RedisConnection::Single { pool, .. } => {
+                Ok(ActorConnection::Single(PooledRedisConnection(pool.get()?)))
+            }
+            RedisConnection::Cluster(client) => {
+                Ok(ActorConnection::Cluster(client.get_connection()?))
+            }
+            RedisConnection::Sentinel(client) => {
+                let mut client = client.lock().unwrap();
+                Ok(ActorConnection::Sentinel(client.get_connection()?))
+            }
+        }
+    }
+}
+
+/// Builds the Redis connection an actor's module will use for the lifetime
+/// of its configuration, based on the values supplied with `OP_CONFIGURE`.
+///
+/// `MODE` selects the topology:
+/// - unset (default): a single node, via the `URL` value (e.g.
+///   `redis://127.0.0.1:6379/`), falling back to a local default.
+/// - `cluster`: a Redis Cluster, via a comma-separated list of seed node
+///   URLs in `CLUSTER_URLS`.
+/// - `sentinel`: a Sentinel-monitored master, via a comma-separated list of
+///   sentinel addresses in `SENTINEL_ADDRESSES` and the master's group name
+///   in `SENTINEL_MASTER`.
+///
+/// `Single` mode additionally reads `POOL_SIZE` (max pooled connections,
+/// default 10) and `POOL_TIMEOUT_MS` (how long to wait for a free connection
+/// before giving up, default 5000).
+pub fn initialize_client(
+    config: CapabilityConfiguration,
+) -> Result<RedisConnection, Box<dyn Error>> {
+    match config.values.get(ENV_REDIS_MODE).map(String::as_str) {
+        Some(MODE_CLUSTER) => {
+            let urls = split_csv(&config, ENV_CLUSTER_URLS)?;
+            let client = redis::cluster::ClusterClient::open(urls)?;
+            Ok(RedisConnection::Cluster(client))
+        }
+        Some(MODE_SENTINEL) => {
+            let addresses = split_csv(&config, ENV_SENTINEL_ADDRESSES)?;
+            let master = config
+                .values
+                .get(ENV_SENTINEL_MASTER)
+                .cloned()
+                .ok_or("sentinel mode requires a SENTINEL_MASTER value")?;
+            let client = redis::sentinel::SentinelClient::build(
+                addresses,
+                master,
+                None,
+                redis::sentinel::SentinelServerType::Master,
+            )?;
+            Ok(RedisConnection::Sentinel(Mutex::new(client)))
+        }
+        Some(other) => Err(format!("unrecognized Redis MODE '{}'", other).into()),
+        None => {
+            let redis_url = config
+                .values
+                .get(ENV_REDIS_URL)
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_REDIS_URL.to_string());
+            let client = redis::Client::open(redis_url.as_str())?;
+            let manager = RedisConnectionManager::new(client.clone());
+
+            let pool_size = config
+                .values
+                .get(ENV_POOL_SIZE)
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| format!("invalid {} '{}'", ENV_POOL_SIZE, v))
+                })
+                .transpose()?
+                .unwrap_or(DEFAULT_POOL_SIZE);
+            let pool_timeout = config
+                .values
+                .get(ENV_POOL_TIMEOUT_MS)
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| format!("invalid {} '{}'", ENV_POOL_TIMEOUT_MS, v))
+                })
+                .transpose()?
+                .unwrap_or(DEFAULT_POOL_TIMEOUT_MS);
+
+            let pool = Pool::builder()
+                .max_size(pool_size)
+                .connection_timeout(Duration::from_millis(pool_timeout))
+                .build(manager)?;
+
+            Ok(RedisConnection::Single { client, pool })
+        }
+    }
+}
+
+fn split_csv(config: &CapabilityConfiguration, key: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let raw = config
+        .values
+        .get(key)
+        .ok_or_else(|| format!("expected a comma-separated {} value", key))?;
+    Ok(raw.split(',').map(str::trim).map(String::from).collect())
+}
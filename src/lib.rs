@@ -25,22 +25,242 @@ use codec::core::CapabilityConfiguration;
 use codec::core::OP_CONFIGURE;
 use codec::keyvalue;
 use keyvalue::*;
+use kvredis::{ActorConnection, RedisConnection};
 use prost::Message;
-use redis::Connection;
-use redis::RedisResult;
-use redis::{self, Commands};
+use redis::{self, Commands, ConnectionLike, ControlFlow, PubSubCommands};
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 const CAPABILITY_ID: &str = "wascc:keyvalue";
 
+/// Actor-issued operation that registers interest in a set of keys or key
+/// patterns. Once accepted, the provider delivers `OP_KEY_CHANGED` calls
+/// back to the watching actor whenever one of those keys changes in Redis.
+const OP_WATCH: &str = "KeyValue.Watch";
+
+/// Host-issued operation (provider -> actor) carrying a `KeyChangeNotification`
+/// for a key the actor previously registered interest in via `OP_WATCH`.
+const OP_KEY_CHANGED: &str = "KeyValue.KeyChanged";
+
+/// Keyspace notification flags enabling both keyspace ("K") and keyevent
+/// ("E") notifications for every event class ("A", the alias for all the
+/// generic and type-specific classes, expired events included).
+const NOTIFY_KEYSPACE_EVENTS: &str = "KEA";
+
+/// Executes many operations in a single Redis pipeline, saving a network
+/// round trip per key.
+const OP_BATCH: &str = "KeyValue.Batch";
+
+/// Sets or clears a key's expiration independently of its value.
+const OP_EXPIRE: &str = "KeyValue.Expire";
+
+/// Like `OP_SET`, but also applies a TTL in the same round trip.
+const OP_SET_EX: &str = "KeyValue.SetEx";
+
+/// Reports the remaining TTL on a key.
+const OP_GET_TTL: &str = "KeyValue.GetTtl";
+
+/// Compare-and-swap: replaces a key's value only if it still matches an
+/// expected value, giving actors safe read-modify-write without an
+/// external lock.
+const OP_CAS: &str = "KeyValue.Cas";
+
+/// Walks the keyspace in bounded chunks via Redis `SCAN`, instead of the
+/// blocking, memory-hungry `KEYS *`.
+const OP_SCAN: &str = "KeyValue.Scan";
+
 capability_provider!(RedisKVProvider, RedisKVProvider::new);
 
+/// Request to watch one or more keys (or glob patterns) for changes.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub keys: Vec<String>,
+}
+
+/// Acknowledges that a watch was (or was not) established.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchResponse {
+    #[prost(bool, tag = "1")]
+    pub watching: bool,
+}
+
+/// Delivered to a watching actor when one of its watched keys changes.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeyChangeNotification {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub event: String,
+}
+
+/// A single operation within an `OP_BATCH` request. Exactly one variant
+/// should be set.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchOp {
+    #[prost(oneof = "batch_op::Op", tags = "1, 2, 3, 4, 5, 6")]
+    pub op: Option<batch_op::Op>,
+}
+
+pub mod batch_op {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Op {
+        #[prost(message, tag = "1")]
+        Get(super::GetRequest),
+        #[prost(message, tag = "2")]
+        Set(super::SetRequest),
+        #[prost(message, tag = "3")]
+        Add(super::AddRequest),
+        #[prost(message, tag = "4")]
+        Del(super::DelRequest),
+        #[prost(message, tag = "5")]
+        ListPush(super::ListPushRequest),
+        #[prost(message, tag = "6")]
+        ListDel(super::ListDelItemRequest),
+    }
+}
+
+/// Runs `ops` as a single Redis pipeline.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub ops: Vec<BatchOp>,
+}
+
+/// The outcome of one `BatchOp`, in the same order as the request.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchResult {
+    #[prost(oneof = "batch_result::Result", tags = "1, 2, 3, 4, 5, 6")]
+    pub result: Option<batch_result::Result>,
+}
+
+pub mod batch_result {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "1")]
+        Get(super::GetResponse),
+        #[prost(message, tag = "2")]
+        Set(super::SetResponse),
+        #[prost(message, tag = "3")]
+        Add(super::AddResponse),
+        #[prost(message, tag = "4")]
+        Del(super::DelResponse),
+        #[prost(message, tag = "5")]
+        ListPush(super::ListResponse),
+        #[prost(message, tag = "6")]
+        ListDel(super::ListResponse),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<BatchResult>,
+}
+
+/// Sets (`ttl_s > 0`) or clears (`ttl_s <= 0`) a key's expiration.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExpireRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(int32, tag = "2")]
+    pub ttl_s: i32,
+}
+
+/// Whether the requested expiration (or persistence) was applied.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExpireResponse {
+    #[prost(bool, tag = "1")]
+    pub applied: bool,
+}
+
+/// Sets `key` to `value`, applying a TTL (`ttl_s > 0`) or clearing any
+/// existing expiration (`ttl_s <= 0`) in the same round trip.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetExRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+    #[prost(int32, tag = "3")]
+    pub ttl_s: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTtlRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+}
+
+/// The remaining TTL on a key, in seconds (`-1` = no expiration, `-2` = key
+/// does not exist), per Redis `TTL` semantics.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTtlResponse {
+    #[prost(int32, tag = "1")]
+    pub ttl_s: i32,
+}
+
+/// Replaces `key`'s value with `new_value` only if its current value is
+/// still `expected`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CasRequest {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(string, tag = "2")]
+    pub expected: String,
+    #[prost(string, tag = "3")]
+    pub new_value: String,
+}
+
+/// Whether the swap happened, along with the value actually stored at
+/// `key` afterwards.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CasResponse {
+    #[prost(bool, tag = "1")]
+    pub swapped: bool,
+    #[prost(string, tag = "2")]
+    pub current: String,
+}
+
+/// One page of a `SCAN` iteration. `cursor` should be 0 on the first call;
+/// `count` is a hint for the page size (0 lets Redis pick a default).
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScanRequest {
+    #[prost(string, tag = "1")]
+    pub pattern: String,
+    #[prost(uint64, tag = "2")]
+    pub cursor: u64,
+    #[prost(uint32, tag = "3")]
+    pub count: u32,
+}
+
+/// A page of matching keys, plus the cursor to pass on the next call. A
+/// `cursor` of 0 means the scan is complete.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScanResponse {
+    #[prost(uint64, tag = "1")]
+    pub cursor: u64,
+    #[prost(string, repeated, tag = "2")]
+    pub keys: Vec<String>,
+}
+
+/// A running keyspace-notification subscriber thread for a single actor,
+/// along with the flag used to ask it to stop.
+struct Watcher {
+    shutdown: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
 pub struct RedisKVProvider {
     dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
-    clients: Arc<RwLock<HashMap<String, redis::Client>>>,
+    clients: Arc<RwLock<HashMap<String, RedisConnection>>>,
+    watchers: Arc<RwLock<HashMap<String, Watcher>>>,
 }
 
 impl Default for RedisKVProvider {
@@ -50,6 +270,7 @@ impl Default for RedisKVProvider {
         RedisKVProvider {
             dispatcher: Arc::new(RwLock::new(Box::new(NullDispatcher::new()))),
             clients: Arc::new(RwLock::new(HashMap::new())),
+            watchers: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -59,18 +280,148 @@ impl RedisKVProvider {
         RedisKVProvider::default()
     }
 
-    fn actor_con(&self, actor: &str) -> RedisResult<Connection> {
+    fn actor_con(&self, actor: &str) -> Result<ActorConnection, Box<dyn Error>> {
         let lock = self.clients.read().unwrap();
-        lock.get(actor).unwrap().get_connection()
+        lock.get(actor)
+            .ok_or_else(|| format!("no Redis client configured for actor {}", actor))?
+            .get_connection()
     }
 
     fn configure(&self, config: CapabilityConfiguration) -> Result<Vec<u8>, Box<dyn Error>> {
         let c = kvredis::initialize_client(config.clone())?;
 
+        // A reconfigure replaces the actor's client outright, so any watcher
+        // thread built on the old one needs to be torn down first.
+        self.stop_watch(&config.module);
+
         self.clients.write().unwrap().insert(config.module, c);
         Ok(vec![])
     }
 
+    /// Registers interest in a set of keys/patterns on behalf of `actor`.
+    /// Enables Redis keyspace notifications, then spawns a dedicated pub/sub
+    /// connection that forwards matching events back to the actor as
+    /// `OP_KEY_CHANGED` calls. A second `watch` call for the same actor
+    /// replaces its previous subscription.
+    fn watch(&self, actor: &str, req: WatchRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        if req.keys.is_empty() {
+            // PSUBSCRIBE with zero patterns is invalid and would make the
+            // background thread die immediately; report that plainly
+            // instead of acking a subscription that was never established.
+            return Ok(bytes(WatchResponse { watching: false }));
+        }
+
+        let client = {
+            let lock = self.clients.read().unwrap();
+            match lock.get(actor) {
+                Some(RedisConnection::Single { client, .. }) => client.clone(),
+                Some(_) => {
+                    return Err(
+                        "OP_WATCH is only supported for single-node Redis connections".into(),
+                    )
+                }
+                None => {
+                    return Err(format!("no Redis client configured for actor {}", actor).into())
+                }
+            }
+        };
+
+        {
+            let mut con = self.actor_con(actor)?;
+            redis::cmd("CONFIG")
+                .arg("SET")
+                .arg("notify-keyspace-events")
+                .arg(NOTIFY_KEYSPACE_EVENTS)
+                .query::<()>(&mut con)?;
+        }
+
+        self.stop_watch(actor);
+
+        let patterns: Vec<String> = req
+            .keys
+            .iter()
+            .map(|k| format!("__keyspace@*__:{}", k))
+            .collect();
+        let dispatcher = self.dispatcher.clone();
+        let actor_name = actor.to_string();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let shutdown_thread = shutdown.clone();
+
+        let handle = thread::spawn(move || {
+            let mut con = match client.get_connection() {
+                Ok(c) => c,
+                Err(e) => {
+                    error!(
+                        "failed to open watch connection for actor {}: {}",
+                        actor_name, e
+                    );
+                    return;
+                }
+            };
+            if let Err(e) = con.set_read_timeout(Some(Duration::from_millis(500))) {
+                error!(
+                    "failed to set watch read timeout for actor {}: {}",
+                    actor_name, e
+                );
+                return;
+            }
+            let pattern_refs: Vec<&str> = patterns.iter().map(String::as_str).collect();
+
+            while !shutdown_thread.load(Ordering::Relaxed) {
+                let result = con.psubscribe(&pattern_refs, |msg| {
+                    if shutdown_thread.load(Ordering::Relaxed) {
+                        return ControlFlow::Break(());
+                    }
+
+                    let channel = msg.get_channel_name();
+                    let key = channel.splitn(2, ':').nth(1).unwrap_or("").to_string();
+                    let event: String = msg.get_payload().unwrap_or_default();
+                    let payload = bytes(KeyChangeNotification { key, event });
+
+                    if let Err(e) =
+                        dispatcher
+                            .read()
+                            .unwrap()
+                            .dispatch(&actor_name, OP_KEY_CHANGED, &payload)
+                    {
+                        error!("failed to dispatch key change to {}: {}", actor_name, e);
+                    }
+
+                    ControlFlow::Continue
+                });
+
+                match result {
+                    Ok(_) => break,
+                    Err(e) if e.is_timeout() => continue,
+                    Err(e) => {
+                        error!("watch subscription for {} ended: {}", actor_name, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        self.watchers
+            .write()
+            .unwrap()
+            .insert(actor.to_string(), Watcher { shutdown, handle });
+
+        Ok(bytes(WatchResponse { watching: true }))
+    }
+
+    /// Signals and joins the watcher thread for `actor`, if one is running.
+    fn stop_watch(&self, actor: &str) {
+        // Remove-then-drop the write guard before joining: the `if let`
+        // scrutinee's temporary would otherwise keep the guard alive across
+        // `join()`, blocking every other actor's `watch`/`configure`/
+        // `stop_watch` call on this shared lock until the thread exits.
+        let watcher = self.watchers.write().unwrap().remove(actor);
+        if let Some(w) = watcher {
+            w.shutdown.store(true, Ordering::Relaxed);
+            let _ = w.handle.join();
+        }
+    }
+
     fn add(&self, actor: &str, req: AddRequest) -> Result<Vec<u8>, Box<dyn Error>> {
         let mut con = self.actor_con(actor)?;
         let res: i32 = con.incr(req.key, req.value)?;
@@ -112,6 +463,18 @@ impl RedisKVProvider {
         }
     }
 
+    /// Reports the remaining TTL on `key`, in seconds (`-1` if it has no
+    /// expiration, `-2` if it does not exist). A local op/type pair because
+    /// `GetResponse` is a foreign `codec::keyvalue` type this crate does not
+    /// own and cannot extend with a `ttl_s` field.
+    fn get_ttl(&self, actor: &str, req: GetTtlRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut con = self.actor_con(actor)?;
+        let ttl_s: i64 = con.ttl(&req.key)?;
+        Ok(bytes(GetTtlResponse {
+            ttl_s: ttl_s as i32,
+        }))
+    }
+
     fn list_clear(&self, actor: &str, req: ListClearRequest) -> Result<Vec<u8>, Box<dyn Error>> {
         self.del(actor, DelRequest { key: req.key })
     }
@@ -136,6 +499,22 @@ impl RedisKVProvider {
         }))
     }
 
+    /// Like `OP_SET`, but also sets (`ttl_s > 0`) or clears (`ttl_s <= 0`)
+    /// the key's expiration in the same round trip. A local op/type pair
+    /// because `SetRequest` is a foreign `codec::keyvalue` type this crate
+    /// does not own and cannot extend with an `expires_s` field.
+    fn set_ex(&self, actor: &str, req: SetExRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut con = self.actor_con(actor)?;
+        if req.ttl_s > 0 {
+            con.set_ex(&req.key, &req.value, req.ttl_s as usize)?;
+        } else {
+            con.set(&req.key, &req.value)?;
+        }
+        Ok(bytes(SetResponse {
+            value: req.value.clone(),
+        }))
+    }
+
     fn list_del_item(
         &self,
         actor: &str,
@@ -188,6 +567,175 @@ impl RedisKVProvider {
             exists: result,
         }))
     }
+
+    /// Sets or clears a key's expiration without touching its value.
+    /// `ttl_s <= 0` removes any existing expiration (`PERSIST`); otherwise
+    /// the key expires in `ttl_s` seconds. Returns whether a timeout was
+    /// actually applied, per Redis's `EXPIRE`/`PERSIST` semantics (e.g. a
+    /// missing key applies neither).
+    fn expire(&self, actor: &str, req: ExpireRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut con = self.actor_con(actor)?;
+        let applied: bool = if req.ttl_s > 0 {
+            con.expire(&req.key, req.ttl_s as usize)?
+        } else {
+            con.persist(&req.key)?
+        };
+        Ok(bytes(ExpireResponse { applied }))
+    }
+
+    /// Compare-and-swap. Each actor call uses its own connection (from
+    /// `actor_con`), so a WATCH established here can never be clobbered by a
+    /// concurrent call from another actor. `redis::transaction` already
+    /// implements the WATCH/MULTI/EXEC dance: it re-runs the closure (which
+    /// re-reads the current value and re-establishes the WATCH) whenever the
+    /// EXEC is aborted by a concurrent write, and otherwise commits and
+    /// returns once.
+    fn cas(&self, actor: &str, req: CasRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut con = self.actor_con(actor)?;
+        let key = req.key.clone();
+
+        let result = redis::transaction(&mut con, &[&key], |con, pipe| {
+            let current: Option<String> = con.get(&key)?;
+            if current.as_deref() != Some(req.expected.as_str()) {
+                return Ok(Some((false, current.unwrap_or_default())));
+            }
+
+            let outcome: Option<()> = pipe.set(&key, &req.new_value).ignore().query(con)?;
+            Ok(outcome.map(|_| (true, req.new_value.clone())))
+        });
+
+        let (swapped, current) = match result {
+            Ok(pair) => pair,
+            Err(e) => {
+                // `redis::transaction` only UNWATCHes on success; if the
+                // closure errored out mid-transaction the WATCH is still
+                // live. Connections are now pooled, so clear it ourselves --
+                // otherwise it would go back to the pool still watching
+                // `key` and could spuriously abort an unrelated future CAS.
+                let _: redis::RedisResult<()> = redis::cmd("UNWATCH").query(&mut con);
+                return Err(e.into());
+            }
+        };
+
+        Ok(bytes(CasResponse { swapped, current }))
+    }
+
+    /// Walks the keyspace a page at a time via `SCAN`, so large keyspaces
+    /// can be enumerated in bounded chunks instead of the `KEYS *` stall.
+    fn scan(&self, actor: &str, req: ScanRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut con = self.actor_con(actor)?;
+        let mut cmd = redis::cmd("SCAN");
+        cmd.arg(req.cursor);
+        if !req.pattern.is_empty() {
+            cmd.arg("MATCH").arg(&req.pattern);
+        }
+        if req.count > 0 {
+            cmd.arg("COUNT").arg(req.count);
+        }
+
+        let (cursor, keys): (u64, Vec<String>) = cmd.query(&mut con)?;
+        Ok(bytes(ScanResponse { cursor, keys }))
+    }
+
+    /// Runs every op in `req` as a single Redis pipeline and reports the
+    /// per-op results in the same order, saving a round trip per key.
+    fn batch(&self, actor: &str, req: BatchRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+        {
+            let lock = self.clients.read().unwrap();
+            if let Some(RedisConnection::Cluster(_)) = lock.get(actor) {
+                // A pipeline can mix keys from unrelated hash slots, which
+                // Redis Cluster rejects with CROSSSLOT -- reject up front
+                // the same way OP_WATCH already does for topologies it
+                // doesn't support, rather than letting the query fail
+                // partway through.
+                return Err("OP_BATCH is not supported against a Redis Cluster connection".into());
+            }
+        }
+
+        let mut con = self.actor_con(actor)?;
+        let mut pipe = redis::pipe();
+
+        for op in &req.ops {
+            match &op.op {
+                Some(batch_op::Op::Get(r)) => {
+                    pipe.get(&r.key);
+                }
+                Some(batch_op::Op::Set(r)) => {
+                    pipe.set(&r.key, &r.value);
+                }
+                Some(batch_op::Op::Add(r)) => {
+                    pipe.incr(&r.key, r.value);
+                }
+                Some(batch_op::Op::Del(r)) => {
+                    pipe.del(&r.key);
+                }
+                Some(batch_op::Op::ListPush(r)) => {
+                    pipe.lpush(&r.key, &r.value);
+                }
+                Some(batch_op::Op::ListDel(r)) => {
+                    pipe.lrem(&r.key, 0, &r.value);
+                }
+                None => return Err("batch operation missing its op".into()),
+            };
+        }
+
+        let values: Vec<redis::Value> = pipe.query(&mut con)?;
+        let results = req
+            .ops
+            .iter()
+            .zip(values)
+            .map(|(op, value)| batch_result(op, value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(bytes(BatchResponse { results }))
+    }
+}
+
+/// Converts the raw reply for a single pipelined op back into its typed
+/// `BatchResult` variant.
+fn batch_result(op: &BatchOp, value: redis::Value) -> Result<BatchResult, Box<dyn Error>> {
+    let result = match &op.op {
+        Some(batch_op::Op::Get(_)) => {
+            let v: Option<String> = redis::from_redis_value(&value)?;
+            batch_result::Result::Get(match v {
+                Some(value) => GetResponse {
+                    value,
+                    exists: true,
+                },
+                None => GetResponse {
+                    value: String::new(),
+                    exists: false,
+                },
+            })
+        }
+        Some(batch_op::Op::Set(r)) => {
+            let _: () = redis::from_redis_value(&value)?;
+            batch_result::Result::Set(SetResponse {
+                value: r.value.clone(),
+            })
+        }
+        Some(batch_op::Op::Add(_)) => {
+            let v: i32 = redis::from_redis_value(&value)?;
+            batch_result::Result::Add(AddResponse { value: v })
+        }
+        Some(batch_op::Op::Del(r)) => {
+            let _: i32 = redis::from_redis_value(&value)?;
+            batch_result::Result::Del(DelResponse { key: r.key.clone() })
+        }
+        Some(batch_op::Op::ListPush(_)) => {
+            let v: i32 = redis::from_redis_value(&value)?;
+            batch_result::Result::ListPush(ListResponse { new_count: v })
+        }
+        Some(batch_op::Op::ListDel(_)) => {
+            let v: i32 = redis::from_redis_value(&value)?;
+            batch_result::Result::ListDel(ListResponse { new_count: v })
+        }
+        None => return Err("batch operation missing its op".into()),
+    };
+
+    Ok(BatchResult {
+        result: Some(result),
+    })
 }
 
 fn bytes(msg: impl prost::Message) -> Vec<u8> {
@@ -241,7 +789,226 @@ impl CapabilityProvider for RedisKVProvider {
             }
             keyvalue::OP_SET_QUERY => self.set_query(actor, SetQueryRequest::decode(msg).unwrap()),
             keyvalue::OP_KEY_EXISTS => self.exists(actor, KeyExistsQuery::decode(msg).unwrap()),
+            OP_WATCH => self.watch(actor, WatchRequest::decode(msg).unwrap()),
+            OP_BATCH => self.batch(actor, BatchRequest::decode(msg).unwrap()),
+            OP_EXPIRE => self.expire(actor, ExpireRequest::decode(msg).unwrap()),
+            OP_SET_EX => self.set_ex(actor, SetExRequest::decode(msg).unwrap()),
+            OP_GET_TTL => self.get_ttl(actor, GetTtlRequest::decode(msg).unwrap()),
+            OP_CAS => self.cas(actor, CasRequest::decode(msg).unwrap()),
+            OP_SCAN => self.scan(actor, ScanRequest::decode(msg).unwrap()),
             _ => Err("bad dispatch".into()),
         }
     }
-}
\ No newline at end of file
+}
+
+/// The `#[ignore]`d tests below exercise `cas()` against a real Redis
+/// (required to observe actual WATCH/UNWATCH behavior) and are skipped by
+/// default; run them with `cargo test -- --ignored` against a Redis
+/// listening on 127.0.0.1:6379.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    /// `redis` provides no blanket `ConnectionLike` impl for `Box<dyn
+    /// ConnectionLike>`, so type-erasing `actor_con`'s return value into one
+    /// doesn't compile -- this would have caught that regression without
+    /// needing a live Redis.
+    #[test]
+    fn actor_connection_is_connection_like_without_boxing() {
+        fn assert_is_connection_like<T: ConnectionLike>() {}
+        assert_is_connection_like::<ActorConnection>();
+    }
+
+    fn configure(actor: &str, pool_size: Option<&str>) -> RedisKVProvider {
+        let mut values = HashMap::new();
+        values.insert("URL".to_string(), "redis://127.0.0.1:6379/".to_string());
+        if let Some(size) = pool_size {
+            values.insert("POOL_SIZE".to_string(), size.to_string());
+        }
+
+        let provider = RedisKVProvider::new();
+        provider
+            .configure(CapabilityConfiguration {
+                module: actor.to_string(),
+                values,
+                ..Default::default()
+            })
+            .expect("configure should succeed against a local Redis");
+        provider
+    }
+
+    fn raw_connection() -> redis::Connection {
+        redis::Client::open("redis://127.0.0.1:6379/")
+            .unwrap()
+            .get_connection()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore]
+    fn cas_swaps_on_match_and_rejects_on_mismatch() {
+        let actor = "cas-basic";
+        let provider = configure(actor, None);
+        let mut con = raw_connection();
+        let _: () = redis::cmd("SET")
+            .arg("cas-basic-key")
+            .arg("v0")
+            .query(&mut con)
+            .unwrap();
+
+        let resp = provider
+            .cas(
+                actor,
+                CasRequest {
+                    key: "cas-basic-key".into(),
+                    expected: "v0".into(),
+                    new_value: "v1".into(),
+                },
+            )
+            .unwrap();
+        let resp = CasResponse::decode(&resp[..]).unwrap();
+        assert!(resp.swapped);
+        assert_eq!(resp.current, "v1");
+
+        let resp = provider
+            .cas(
+                actor,
+                CasRequest {
+                    key: "cas-basic-key".into(),
+                    expected: "v0".into(),
+                    new_value: "v2".into(),
+                },
+            )
+            .unwrap();
+        let resp = CasResponse::decode(&resp[..]).unwrap();
+        assert!(!resp.swapped);
+        assert_eq!(resp.current, "v1");
+    }
+
+    #[test]
+    #[ignore]
+    fn cas_reports_mismatch_during_a_concurrent_write() {
+        let actor = "cas-retry";
+        let provider = configure(actor, None);
+        let key = "cas-retry-key";
+        let mut con = raw_connection();
+        let _: () = redis::cmd("SET")
+            .arg(key)
+            .arg("v0")
+            .query(&mut con)
+            .unwrap();
+
+        // Hammer the key from another connection for the duration of the CAS
+        // call. Block on the writer's first SET before starting the CAS, so
+        // there's no window where the CAS could legitimately finish first
+        // and see the key still at "v0".
+        let (started_tx, started_rx) = mpsc::channel();
+        let writer = thread::spawn(move || {
+            let mut con = raw_connection();
+            for i in 0..200 {
+                let _: redis::RedisResult<()> = redis::cmd("SET")
+                    .arg(key)
+                    .arg(format!("writer-{}", i))
+                    .query(&mut con);
+                if i == 0 {
+                    let _ = started_tx.send(());
+                }
+            }
+        });
+        started_rx.recv().unwrap();
+
+        let resp = provider
+            .cas(
+                actor,
+                CasRequest {
+                    key: key.into(),
+                    expected: "v0".into(),
+                    new_value: "cas-won".into(),
+                },
+            )
+            .unwrap();
+        writer.join().unwrap();
+        let resp = CasResponse::decode(&resp[..]).unwrap();
+
+        // The writer has already replaced "v0" by the time our CAS reads
+        // `key`, so it should report a clean mismatch rather than an error
+        // or a value we never wrote ourselves.
+        assert!(!resp.swapped);
+        assert_ne!(resp.current, "cas-won");
+    }
+
+    #[test]
+    #[ignore]
+    fn cas_error_does_not_leave_a_dangling_watch_on_the_pooled_connection() {
+        let actor = "cas-unwatch";
+        // A single-connection pool guarantees that everything below reuses
+        // the exact connection the failing CAS runs on.
+        let provider = configure(actor, Some("1"));
+        let mut con = raw_connection();
+
+        let _: () = redis::cmd("DEL")
+            .arg("cas-unwatch-bad")
+            .query(&mut con)
+            .unwrap();
+        let _: () = redis::cmd("LPUSH")
+            .arg("cas-unwatch-bad")
+            .arg("x")
+            .query(&mut con)
+            .unwrap();
+
+        // GET against a list key fails with WRONGTYPE, so the transaction
+        // closure errors out before EXEC and the connection goes back to
+        // the pool through the error path.
+        let err = provider.cas(
+            actor,
+            CasRequest {
+                key: "cas-unwatch-bad".into(),
+                expected: "anything".into(),
+                new_value: "y".into(),
+            },
+        );
+        assert!(err.is_err());
+
+        // An unrelated write lands on the failed key after the error. If it
+        // were still WATCHed on the pooled connection, the next transaction
+        // on that connection would abort: EXEC replies nil instead of an
+        // (empty) array. `redis::transaction` would silently retry such an
+        // abort, so check it with a bare MULTI/EXEC rather than via `cas()`.
+        let _: () = redis::cmd("LPUSH")
+            .arg("cas-unwatch-bad")
+            .arg("y")
+            .query(&mut con)
+            .unwrap();
+        {
+            let mut pooled = provider.actor_con(actor).unwrap();
+            let _: () = redis::cmd("MULTI").query(&mut pooled).unwrap();
+            let exec: Option<()> = redis::cmd("EXEC").query(&mut pooled).unwrap();
+            assert!(
+                exec.is_some(),
+                "a dangling WATCH from the earlier error aborted an unrelated transaction"
+            );
+        }
+        let _: () = redis::cmd("SET")
+            .arg("cas-unwatch-good")
+            .arg("v0")
+            .query(&mut con)
+            .unwrap();
+
+        let resp = provider
+            .cas(
+                actor,
+                CasRequest {
+                    key: "cas-unwatch-good".into(),
+                    expected: "v0".into(),
+                    new_value: "v1".into(),
+                },
+            )
+            .unwrap();
+        let resp = CasResponse::decode(&resp[..]).unwrap();
+        assert!(
+            resp.swapped,
+            "an unrelated CAS on the reused pooled connection failed to swap"
+        );
+    }
+}